@@ -1,6 +1,6 @@
 #![allow(dead_code)]
 
-use std::{cell::RefCell, fmt::Debug, ops::{Deref, DerefMut}};
+use std::{cell::{Cell, UnsafeCell}, fmt::Debug, marker::PhantomData, ops::{Deref, DerefMut}};
 
 #[derive(Debug, PartialEq, Eq)]
 struct Health(i32);
@@ -21,6 +21,15 @@ struct World {
     component_vecs: Vec<Box<dyn ComponentVec>>,
     entities: Vec<Entity>,
     free_entities: Vec<Entity>,
+    component_bits: Vec<(std::any::TypeId, u32)>,
+    component_masks: Vec<u32>,
+    systems: Vec<Box<dyn System>>,
+}
+
+/// A unit of frame-by-frame behavior. A system joins the components it cares
+/// about through [`World::query`]/[`World::query_mut`] and acts on every match.
+trait System {
+    fn run(&mut self, world: &World);
 }
 
 fn print_type<T>(_: T) {
@@ -34,12 +43,32 @@ impl World {
             component_vecs: Vec::new(),
             entities: Vec::new(),
             free_entities: Vec::new(),
+            component_bits: Vec::new(),
+            component_masks: Vec::new(),
+            systems: Vec::new(),
+        }
+    }
+
+    fn add_system<S: System + 'static>(&mut self, system: S) {
+        self.systems.push(Box::new(system));
+    }
+
+    fn run_systems(&mut self) {
+        let mut systems = std::mem::take(&mut self.systems);
+        for system in systems.iter_mut() {
+            system.run(self);
         }
+        self.systems = systems;
+    }
+
+    fn tick(&mut self) {
+        self.run_systems();
     }
 
     fn new_entity(&mut self) -> Entity {
         if let Some(entity) = self.free_entities.pop() {
             self.entities[entity.id] = entity;
+            self.component_masks[entity.id] = 0;
             entity
         } else {
             let entity = Entity {
@@ -53,51 +82,260 @@ impl World {
             }
 
             self.entities.push(entity);
+            self.component_masks.push(0);
             entity
         }
     }
 
+    /// Assigns `ComponentType` a unique membership bit (`2^n`) and allocates its
+    /// backing store up front, so queries can filter by an integer mask test
+    /// instead of probing every column's `Option` slots. Returns a [`Key`] into
+    /// the store for fast repeated access via [`World::get_with_key`].
+    fn register_component<ComponentType: 'static + std::fmt::Debug>(&mut self) -> Key<ComponentType> {
+        if let Some(id) = self.store_index::<ComponentType>() {
+            return Key::new(id);
+        }
+
+        assert!(
+            self.component_bits.len() < 32,
+            "cannot register more than 32 component types in a 32-bit mask",
+        );
+        let bit = 1u32 << self.component_bits.len();
+        self.component_bits.push((std::any::TypeId::of::<ComponentType>(), bit));
+        let id = self.component_vecs.len();
+        self.component_vecs.push(Box::new(Components::<ComponentType>::new(self.entities_count)));
+        Key::new(id)
+    }
+
+    fn store_index<ComponentType: 'static>(&self) -> Option<usize> {
+        let type_id = std::any::TypeId::of::<ComponentType>();
+        self.component_vecs.iter().position(|cv| cv.get_type_id() == type_id)
+    }
+
+    /// Reads a component through a [`Key`], indexing straight into
+    /// `component_vecs[key.id]` and downcasting once — no linear `TypeId` scan.
+    fn get_with_key<ComponentType: 'static>(&self, key: Key<ComponentType>, entity: Entity) -> Option<RefComponent<'_, ComponentType>> {
+        if self.entities.get(entity.id) != Some(&entity) {
+            return None;
+        }
+
+        let components = self.component_vecs[key.id].as_any().downcast_ref::<Components<ComponentType>>()?;
+        let r = components.get(entity.id);
+        if r.is_some() {
+            Some(r)
+        } else {
+            None
+        }
+    }
+
+    fn bit_of<ComponentType: 'static>(&self) -> Option<u32> {
+        let type_id = std::any::TypeId::of::<ComponentType>();
+        self.component_bits.iter().find(|(tid, _)| *tid == type_id).map(|(_, bit)| *bit)
+    }
+
+    fn despawn(&mut self, entity: Entity) {
+        let Some(ent) = self.entities.get(entity.id) else {
+            return;
+        };
+        if *ent != entity {
+            return;
+        }
+
+        for component_vec in self.component_vecs.iter_mut() {
+            component_vec.remove(entity.id);
+        }
+
+        let recycled = Entity {
+            id: entity.id,
+            gen: entity.gen + 1,
+        };
+        self.component_masks[entity.id] = 0;
+        self.entities[entity.id] = recycled;
+        self.free_entities.push(recycled);
+    }
+
     fn add_component_to_entity<ComponentType: 'static + std::fmt::Debug>(
         &mut self,
         entity: Entity,
         component: ComponentType,
     ) {
+        if self.bit_of::<ComponentType>().is_none() {
+            self.register_component::<ComponentType>();
+        }
+
         for component_vec in self.component_vecs.iter_mut() {
             if let Some(components) = component_vec.as_any_mut().downcast_mut::<Components<ComponentType>>() {
                 components.set(entity.id, component);
-                return;
+                break;
             }
         }
 
-        // new one
-        let mut components = Components::new(self.entities_count);
-        components.set(entity.id, component);
+        let bit = self.bit_of::<ComponentType>().expect("component type was just registered");
+        self.component_masks[entity.id] |= bit;
+    }
+
+    fn get_component<ComponentType: 'static>(&self, entity: Entity) -> Option<RefComponent<'_, ComponentType>> {
+        if self.entities.get(entity.id) != Some(&entity) {
+            return None;
+        }
 
-        self.component_vecs.push(Box::new(components));
+        let components = self.components_of::<ComponentType>()?;
+        Some(components.get(entity.id))
     }
 
-    fn get_component<ComponentType: 'static>(&self, entity: Entity) -> Option<RefComponent<ComponentType>>{
-        if let Some(ent) = self.entities.get(entity.id) {
-            if *ent == entity {
-                for components in self.component_vecs.iter() {
-                    if let Some(components) = components.as_any().downcast_ref::<Components<ComponentType>>() {
-                        return Some(components.get(entity.id));
-                    }
-                }
+    fn get_component_mut<ComponentType: 'static>(&self, entity: Entity) -> Option<RefMutComponent<'_, ComponentType>> {
+        if self.entities.get(entity.id) != Some(&entity) {
+            return None;
+        }
+
+        let components = self.components_of::<ComponentType>()?;
+        Some(components.get_mut(entity.id))
+    }
+
+    fn remove_component<ComponentType: 'static>(&mut self, entity: Entity) {
+        if self.entities.get(entity.id) != Some(&entity) {
+            return;
+        }
+
+        let type_id = std::any::TypeId::of::<ComponentType>();
+        for component_vec in self.component_vecs.iter_mut() {
+            if component_vec.get_type_id() == type_id {
+                component_vec.remove(entity.id);
+                break;
+            }
+        }
+
+        if let Some(bit) = self.bit_of::<ComponentType>() {
+            self.component_masks[entity.id] &= !bit;
+        }
+    }
+
+    fn has_component<ComponentType: 'static>(&self, entity: Entity) -> bool {
+        if self.entities.get(entity.id) != Some(&entity) {
+            return false;
+        }
+
+        match self.components_of::<ComponentType>() {
+            Some(components) => components.get(entity.id).is_some(),
+            None => false,
+        }
+    }
+
+    fn components_of<ComponentType: 'static>(&self) -> Option<&Components<ComponentType>> {
+        let id = self.store_index::<ComponentType>()?;
+        self.component_vecs[id].as_any().downcast_ref::<Components<ComponentType>>()
+    }
+
+    fn fetch_ref<ComponentType: 'static>(&self, idx: usize) -> Option<RefComponent<'_, ComponentType>> {
+        let components = self.components_of::<ComponentType>()?;
+        let r = components.get(idx);
+        if r.is_some() {
+            Some(r)
+        } else {
+            None
+        }
+    }
+
+    fn fetch_mut<ComponentType: 'static>(&self, idx: usize) -> Option<RefMutComponent<'_, ComponentType>> {
+        let components = self.components_of::<ComponentType>()?;
+        let r = components.get_mut(idx);
+        if r.is_some() {
+            Some(r)
+        } else {
+            None
+        }
+    }
+
+    fn query<Q: Query>(&self) -> QueryIter<'_, Q> {
+        QueryIter {
+            world: self,
+            idx: 0,
+            want: Q::want(self),
+            _marker: PhantomData,
+        }
+    }
+
+    fn query_mut<Q: QueryMut>(&self) -> QueryMutIter<'_, Q> {
+        QueryMutIter {
+            world: self,
+            idx: 0,
+            want: Q::want(self),
+            _marker: PhantomData,
+        }
+    }
+}
+
+/// A join over several component types, yielding one tuple per entity that owns
+/// every requested component. Implemented for tuples of component types so that
+/// `world.query::<(Health, Speed)>()` walks the columns in lockstep.
+trait Query {
+    type Item<'a>;
+
+    /// The combined membership mask of the requested types, or `None` when a
+    /// requested type was never registered (so nothing can match).
+    fn want(world: &World) -> Option<u32>;
+
+    fn fetch(world: &World, entity: Entity, idx: usize) -> Option<Self::Item<'_>>;
+}
+
+/// The `get_mut` counterpart of [`Query`], handing out [`RefMutComponent`]s.
+trait QueryMut {
+    type Item<'a>;
+
+    fn want(world: &World) -> Option<u32>;
+
+    fn fetch(world: &World, entity: Entity, idx: usize) -> Option<Self::Item<'_>>;
+}
+
+struct QueryIter<'a, Q: Query> {
+    world: &'a World,
+    idx: usize,
+    want: Option<u32>,
+    _marker: PhantomData<Q>,
+}
+
+impl<'a, Q: Query> Iterator for QueryIter<'a, Q> {
+    type Item = Q::Item<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let want = self.want?;
+        while self.idx < self.world.entities_count {
+            let idx = self.idx;
+            self.idx += 1;
+            if self.world.component_masks[idx] & want != want {
+                continue;
+            }
+            let entity = self.world.entities[idx];
+            if let Some(item) = Q::fetch(self.world, entity, idx) {
+                return Some(item);
             }
         }
 
         None
     }
+}
+
+struct QueryMutIter<'a, Q: QueryMut> {
+    world: &'a World,
+    idx: usize,
+    want: Option<u32>,
+    _marker: PhantomData<Q>,
+}
 
-    fn get_component_mut<ComponentType: 'static>(&self, entity: Entity) -> Option<RefMutComponent<ComponentType>> {
-        if let Some(ent) = self.entities.get(entity.id) {
-            if *ent == entity{
-                for components in self.component_vecs.iter() {
-                    if let Some(components) = components.as_any().downcast_ref::<Components<ComponentType>>() {
-                        return Some(components.get_mut(entity.id));
-                    }
-                }
+impl<'a, Q: QueryMut> Iterator for QueryMutIter<'a, Q> {
+    type Item = Q::Item<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let want = self.want?;
+        while self.idx < self.world.entities_count {
+            let idx = self.idx;
+            self.idx += 1;
+            if self.world.component_masks[idx] & want != want {
+                continue;
+            }
+            let entity = self.world.entities[idx];
+            if let Some(item) = Q::fetch(self.world, entity, idx) {
+                return Some(item);
             }
         }
 
@@ -105,51 +343,208 @@ impl World {
     }
 }
 
+impl<A: 'static> Query for (A,) {
+    type Item<'a> = (Entity, RefComponent<'a, A>);
+
+    fn want(world: &World) -> Option<u32> {
+        world.bit_of::<A>()
+    }
+
+    fn fetch(world: &World, entity: Entity, idx: usize) -> Option<Self::Item<'_>> {
+        Some((entity, world.fetch_ref::<A>(idx)?))
+    }
+}
+
+impl<A: 'static, B: 'static> Query for (A, B) {
+    type Item<'a> = (Entity, RefComponent<'a, A>, RefComponent<'a, B>);
+
+    fn want(world: &World) -> Option<u32> {
+        Some(world.bit_of::<A>()? | world.bit_of::<B>()?)
+    }
+
+    fn fetch(world: &World, entity: Entity, idx: usize) -> Option<Self::Item<'_>> {
+        let a = world.fetch_ref::<A>(idx)?;
+        let b = world.fetch_ref::<B>(idx)?;
+        Some((entity, a, b))
+    }
+}
+
+impl<A: 'static, B: 'static, C: 'static> Query for (A, B, C) {
+    type Item<'a> = (Entity, RefComponent<'a, A>, RefComponent<'a, B>, RefComponent<'a, C>);
+
+    fn want(world: &World) -> Option<u32> {
+        Some(world.bit_of::<A>()? | world.bit_of::<B>()? | world.bit_of::<C>()?)
+    }
+
+    fn fetch(world: &World, entity: Entity, idx: usize) -> Option<Self::Item<'_>> {
+        let a = world.fetch_ref::<A>(idx)?;
+        let b = world.fetch_ref::<B>(idx)?;
+        let c = world.fetch_ref::<C>(idx)?;
+        Some((entity, a, b, c))
+    }
+}
+
+impl<A: 'static> QueryMut for (A,) {
+    type Item<'a> = (Entity, RefMutComponent<'a, A>);
+
+    fn want(world: &World) -> Option<u32> {
+        world.bit_of::<A>()
+    }
+
+    fn fetch(world: &World, entity: Entity, idx: usize) -> Option<Self::Item<'_>> {
+        Some((entity, world.fetch_mut::<A>(idx)?))
+    }
+}
+
+impl<A: 'static, B: 'static> QueryMut for (A, B) {
+    type Item<'a> = (Entity, RefMutComponent<'a, A>, RefMutComponent<'a, B>);
+
+    fn want(world: &World) -> Option<u32> {
+        Some(world.bit_of::<A>()? | world.bit_of::<B>()?)
+    }
+
+    fn fetch(world: &World, entity: Entity, idx: usize) -> Option<Self::Item<'_>> {
+        let a = world.fetch_mut::<A>(idx)?;
+        let b = world.fetch_mut::<B>(idx)?;
+        Some((entity, a, b))
+    }
+}
+
+impl<A: 'static, B: 'static, C: 'static> QueryMut for (A, B, C) {
+    type Item<'a> = (Entity, RefMutComponent<'a, A>, RefMutComponent<'a, B>, RefMutComponent<'a, C>);
+
+    fn want(world: &World) -> Option<u32> {
+        Some(world.bit_of::<A>()? | world.bit_of::<B>()? | world.bit_of::<C>()?)
+    }
+
+    fn fetch(world: &World, entity: Entity, idx: usize) -> Option<Self::Item<'_>> {
+        let a = world.fetch_mut::<A>(idx)?;
+        let b = world.fetch_mut::<B>(idx)?;
+        let c = world.fetch_mut::<C>(idx)?;
+        Some((entity, a, b, c))
+    }
+}
+
+/// A typed handle to a component store, handed out by
+/// [`World::register_component`]. Carries the store index so hot loops can skip
+/// the per-access `TypeId` scan in [`World::get_component`].
+struct Key<T: 'static> {
+    id: usize,
+    _marker: PhantomData<T>,
+}
+
+impl<T: 'static> Key<T> {
+    fn new(id: usize) -> Self {
+        Self { id, _marker: PhantomData }
+    }
+}
+
+impl<T: 'static> Clone for Key<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T: 'static> Copy for Key<T> {}
+
 trait ComponentVec : Debug{
     fn push_none(&mut self);
+    fn remove(&mut self, idx: usize);
+    fn get_type_id(&self) -> std::any::TypeId;
     fn as_any(&self) -> &dyn std::any::Any;
     fn as_any_mut(&mut self) -> &mut dyn std::any::Any;
 }
 
-#[derive(Debug)]
+/// A column of components indexed by entity id.
+///
+/// Borrow state is tracked per index rather than per column: `borrows[idx]` is
+/// `0` when the slot is free, positive while it is shared-borrowed (the count of
+/// live [`RefComponent`]s), and `-1` while it is uniquely borrowed by a
+/// [`RefMutComponent`]. This lets a join hand out mutable references to distinct
+/// entities of the same type at once, which the previous whole-column `RefCell`
+/// forbade.
 struct Components<T: 'static> {
-    components: RefCell<Vec<Option<T>>>,
+    components: UnsafeCell<Vec<Option<T>>>,
+    borrows: Vec<Cell<isize>>,
 }
 
 impl<T: 'static> Components<T> {
     pub fn new(size: usize) -> Self {
-        let mut components = RefCell::new(Vec::with_capacity(size));
+        let mut components = Vec::with_capacity(size);
+        let mut borrows = Vec::with_capacity(size);
         for _ in 0..size {
-            components.get_mut().push(None);
+            components.push(None);
+            borrows.push(Cell::new(0));
         }
 
-        Self { components }
+        Self {
+            components: UnsafeCell::new(components),
+            borrows,
+        }
     }
 
     pub fn set(&mut self, idx: usize, component: T) {
         self.components.get_mut()[idx] = Some(component);
     }
 
-    pub fn get(&self, idx: usize) -> RefComponent<T> {
-        let r = self.components.borrow();
-        RefComponent {
-            refer: r,
-            idx: idx,
+    pub fn get(&self, idx: usize) -> RefComponent<'_, T> {
+        assert!(idx < self.borrows.len(), "component index {idx} out of bounds");
+        let flag = &self.borrows[idx];
+        let count = flag.get();
+        if count < 0 {
+            panic!("component at index {idx} is already mutably borrowed");
+        }
+        flag.set(count + 1);
+
+        // SAFETY: `idx` is in bounds (asserted above) and the flag guards this
+        // slot against an overlapping unique borrow. We derive a pointer to the
+        // single element and reference only that, never reborrowing the whole
+        // `Vec`, so guards to distinct indices do not invalidate each other.
+        let slot = unsafe { (*self.components.get()).as_ptr().add(idx) };
+        RefComponent { value: unsafe { &*slot }, flag }
+    }
+
+    pub fn get_mut(&self, idx: usize) -> RefMutComponent<'_, T> {
+        assert!(idx < self.borrows.len(), "component index {idx} out of bounds");
+        let flag = &self.borrows[idx];
+        if flag.get() != 0 {
+            panic!("component at index {idx} is already borrowed");
         }
+        flag.set(-1);
+
+        // SAFETY: `idx` is in bounds (asserted above) and the flag was `0`, so
+        // no other reference to this slot exists. We derive a pointer to the
+        // single element and reference only that, never reborrowing the whole
+        // `Vec`, so a `&mut` to one index does not invalidate guards to others.
+        let slot = unsafe { (*self.components.get()).as_mut_ptr().add(idx) };
+        RefMutComponent { value: unsafe { &mut *slot }, flag }
     }
+}
 
-    pub fn get_mut(&self, idx: usize) -> RefMutComponent<T> {
-        let r = self.components.borrow_mut();
-        RefMutComponent {
-            r,
-            idx,
+impl<T: Debug> Debug for Components<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.borrows.iter().any(|flag| flag.get() < 0) {
+            panic!("cannot format components while one is mutably borrowed");
         }
+        // SAFETY: no slot is uniquely borrowed (checked above), so a shared read
+        // of the whole `Vec` for formatting aliases no live `&mut`.
+        let components = unsafe { &*self.components.get() };
+        f.debug_struct("Components").field("components", components).finish()
     }
 }
 
 impl<T: 'static + std::fmt::Debug + std::any::Any> ComponentVec for Components<T> {
     fn push_none(&mut self) {
         self.components.get_mut().push(None);
+        self.borrows.push(Cell::new(0));
+    }
+
+    fn remove(&mut self, idx: usize) {
+        self.components.get_mut()[idx] = None;
+    }
+
+    fn get_type_id(&self) -> std::any::TypeId {
+        std::any::TypeId::of::<T>()
     }
 
     fn as_any(&self) -> &dyn std::any::Any {
@@ -162,34 +557,46 @@ impl<T: 'static + std::fmt::Debug + std::any::Any> ComponentVec for Components<T
 }
 
 struct RefComponent<'a, T: 'static> {
-    refer: std::cell::Ref<'a, Vec<Option<T>>>,
-    idx: usize,
+    value: &'a Option<T>,
+    flag: &'a Cell<isize>,
 }
 
 impl<'a, T: 'static> std::ops::Deref for RefComponent<'a, T> {
     type Target = Option<T>;
 
     fn deref(&self) -> &Self::Target {
-        self.refer.get(self.idx).unwrap()
+        self.value
+    }
+}
+
+impl<'a, T: 'static> Drop for RefComponent<'a, T> {
+    fn drop(&mut self) {
+        self.flag.set(self.flag.get() - 1);
     }
 }
 
 struct RefMutComponent<'a, T: 'static> {
-    r: std::cell::RefMut<'a, Vec<Option<T>>>,
-    idx: usize,
+    value: &'a mut Option<T>,
+    flag: &'a Cell<isize>,
 }
 
 impl <'a, T: 'static> Deref for RefMutComponent<'a, T> {
     type Target = Option<T>;
 
     fn deref(&self) -> &Self::Target {
-        self.r.get(self.idx).unwrap()
+        self.value
     }
 }
 
 impl <'a, T: 'static> DerefMut for RefMutComponent<'a, T> {
     fn deref_mut(&mut self) -> &mut Self::Target {
-        self.r.get_mut(self.idx).unwrap()
+        self.value
+    }
+}
+
+impl<'a, T: 'static> Drop for RefMutComponent<'a, T> {
+    fn drop(&mut self) {
+        self.flag.set(0);
     }
 }
 
@@ -218,6 +625,181 @@ fn test_world() {
     speed.0 = 1000;
 }
 
+#[test]
+fn test_query() {
+    let mut world = World::new();
+
+    let entity_1 = world.new_entity();
+    world.add_component_to_entity(entity_1, Health(10));
+
+    let entity_2 = world.new_entity();
+    world.add_component_to_entity(entity_2, Health(20));
+    world.add_component_to_entity(entity_2, Speed(100));
+
+    // only entity_2 has both Health and Speed
+    let mut matched = Vec::new();
+    for (entity, health, speed) in world.query::<(Health, Speed)>() {
+        matched.push((entity, health.as_ref().unwrap().0, speed.as_ref().unwrap().0));
+    }
+    assert_eq!(matched, vec![(entity_2, 20, 100)]);
+
+    // query_mut lets a system write back through the join
+    for (_entity, mut speed) in world.query_mut::<(Speed,)>() {
+        speed.as_mut().unwrap().0 += 1;
+    }
+    let speed = world.get_component::<Speed>(entity_2).unwrap();
+    assert_eq!(speed.as_ref().unwrap().0, 101);
+}
+
+#[test]
+fn test_despawn() {
+    let mut world = World::new();
+
+    let entity_1 = world.new_entity();
+    world.add_component_to_entity(entity_1, Health(10));
+
+    world.despawn(entity_1);
+
+    // the stale handle no longer resolves ...
+    assert!(world.get_component::<Health>(entity_1).is_none());
+
+    // ... and the slot is recycled with a bumped generation
+    let entity_2 = world.new_entity();
+    assert_eq!(entity_2.id, entity_1.id);
+    assert_eq!(entity_2.gen, entity_1.gen + 1);
+
+    // the old handle must not alias the recycled entity
+    world.add_component_to_entity(entity_2, Health(20));
+    assert!(world.get_component::<Health>(entity_1).is_none());
+    assert_eq!(world.get_component::<Health>(entity_2).unwrap().as_ref().unwrap().0, 20);
+}
+
+#[test]
+fn test_remove_and_has_component() {
+    let mut world = World::new();
+
+    let entity = world.new_entity();
+    world.add_component_to_entity(entity, Health(10));
+    world.add_component_to_entity(entity, Speed(5));
+
+    assert!(world.has_component::<Health>(entity));
+    assert!(world.has_component::<Speed>(entity));
+
+    world.remove_component::<Health>(entity);
+    assert!(!world.has_component::<Health>(entity));
+    assert!(world.has_component::<Speed>(entity));
+}
+
+#[test]
+fn test_register_and_mask_query() {
+    let mut world = World::new();
+    world.register_component::<Health>();
+    world.register_component::<Speed>();
+
+    // distinct powers of two
+    assert_eq!(world.bit_of::<Health>(), Some(0b01));
+    assert_eq!(world.bit_of::<Speed>(), Some(0b10));
+
+    let entity_1 = world.new_entity();
+    world.add_component_to_entity(entity_1, Health(10));
+
+    let entity_2 = world.new_entity();
+    world.add_component_to_entity(entity_2, Health(20));
+    world.add_component_to_entity(entity_2, Speed(100));
+
+    assert_eq!(world.component_masks[entity_1.id], 0b01);
+    assert_eq!(world.component_masks[entity_2.id], 0b11);
+
+    // the mask filter keeps only the entity owning both bits
+    let matched: Vec<_> = world.query::<(Health, Speed)>().map(|(e, _, _)| e).collect();
+    assert_eq!(matched, vec![entity_2]);
+
+    // removing a component clears its bit
+    world.remove_component::<Speed>(entity_2);
+    assert_eq!(world.component_masks[entity_2.id], 0b01);
+    assert_eq!(world.query::<(Health, Speed)>().count(), 0);
+}
+
+#[test]
+fn test_system_scheduler() {
+    // moves each entity by applying its Speed to a positional Health stand-in
+    struct ApplySpeed;
+    impl System for ApplySpeed {
+        fn run(&mut self, world: &World) {
+            for (_entity, mut health, speed) in world.query_mut::<(Health, Speed)>() {
+                health.as_mut().unwrap().0 += speed.as_ref().unwrap().0;
+            }
+        }
+    }
+
+    let mut world = World::new();
+
+    let entity = world.new_entity();
+    world.add_component_to_entity(entity, Health(0));
+    world.add_component_to_entity(entity, Speed(5));
+
+    world.add_system(ApplySpeed);
+    world.tick();
+    world.tick();
+
+    assert_eq!(world.get_component::<Health>(entity).unwrap().as_ref().unwrap().0, 10);
+}
+
+#[test]
+fn test_disjoint_mut_borrows() {
+    let mut world = World::new();
+
+    let entity_1 = world.new_entity();
+    world.add_component_to_entity(entity_1, Speed(1));
+
+    let entity_2 = world.new_entity();
+    world.add_component_to_entity(entity_2, Speed(2));
+
+    // per-slot tracking lets us hold mutable handles to two entities of the
+    // same type at once, which the old whole-column RefCell would panic on.
+    let mut held: Vec<_> = world.query_mut::<(Speed,)>().collect();
+    for (_entity, speed) in held.iter_mut() {
+        speed.as_mut().unwrap().0 *= 10;
+    }
+    drop(held);
+
+    assert_eq!(world.get_component::<Speed>(entity_1).unwrap().as_ref().unwrap().0, 10);
+    assert_eq!(world.get_component::<Speed>(entity_2).unwrap().as_ref().unwrap().0, 20);
+}
+
+#[test]
+#[should_panic]
+fn test_conflicting_borrow_panics() {
+    let mut world = World::new();
+    let entity = world.new_entity();
+    world.add_component_to_entity(entity, Speed(1));
+
+    let _a = world.get_component_mut::<Speed>(entity).unwrap();
+    // second unique borrow of the same slot must panic
+    let _b = world.get_component_mut::<Speed>(entity).unwrap();
+}
+
+#[test]
+fn test_get_with_key() {
+    let mut world = World::new();
+    let speed_key = world.register_component::<Speed>();
+
+    let entity = world.new_entity();
+    world.add_component_to_entity(entity, Speed(42));
+
+    // registering again returns the same store index
+    assert_eq!(world.register_component::<Speed>().id, speed_key.id);
+
+    {
+        let speed = world.get_with_key(speed_key, entity).unwrap();
+        assert_eq!(speed.as_ref().unwrap().0, 42);
+    }
+
+    // a stale handle does not resolve through the key path either
+    world.despawn(entity);
+    assert!(world.get_with_key(speed_key, entity).is_none());
+}
+
 #[test]
 fn test_downcast() {
 